@@ -5,19 +5,27 @@ import (
 	"context"
 	"errors"
 	"fmt"
+	"hash/fnv"
 	"log"
 	"math/rand"
 	"net"
+	"net/url"
 	"os"
 	"os/signal"
+	"path"
+	"strconv"
 	"strings"
 	"sync"
 	"sync/atomic"
 	"syscall"
 	"time"
+	_ "time/tzdata" // /tz must work without the host's zoneinfo
 	"unicode"
+	"unicode/utf8"
 
 	"github.com/gliderlabs/ssh"
+	gossh "golang.org/x/crypto/ssh"
+	"golang.org/x/text/unicode/norm"
 )
 
 type Message struct {
@@ -27,41 +35,106 @@ type Message struct {
 	Color    int
 	IP       string
 	Mentions []string // List of mentioned usernames
+
+	// ANSI-formatted parts shared by every client, filled once by prerender
+	coloredNick string
+	highlighted string
 }
 
 type ChatServer struct {
-	mu       sync.RWMutex
-	messages []Message
-	clients  map[*Client]struct{}
+	mu           sync.RWMutex
+	messages     []Message
+	clients      map[*Client]struct{}
+	vote         *kickVote
+	lastVoteKick map[string]time.Time // initiator IP -> last /votekick
+	muted        map[string]time.Time // IP -> end of its mute
+
+	// Indexes over clients, maintained by AddClient/RemoveClient
+	byIP       map[string]map[*Client]struct{}
+	bySkeleton map[string]*Client // nicknameSkeleton -> client
+
+	// Join/leave notice coalescing, see AnnouncePresence
+	presenceAt      time.Time
+	presencePending *presenceBatch
 }
 
 var (
 	globalChat   = NewChatServer()
 	guestCounter uint64
-	rateLimiter  = NewConnectionRateLimiter()
+	rateLimiter  = NewConnectionRateLimiter(5)
+
+	// attemptLimiter throttles raw TCP connection attempts before the SSH
+	// handshake, so reconnect loops are cut off without costing a handshake.
+	attemptLimiter = NewConnectionRateLimiter(20)
 )
 
-// BanManager keeps a set of banned IP addresses.
+// BanEntry records who was banned, by whom and why.
+type BanEntry struct {
+	ID       uint64
+	IP       string
+	Nickname string // offending nickname(s), if known
+	IssuedBy string // nickname of the user or name of the subsystem
+	Reason   string
+	Message  string // message that triggered the ban, if any
+	Time     time.Time
+	Expires  time.Time // zero for a permanent ban
+}
+
+// BanManager keeps the banned IP addresses.
 type BanManager struct {
 	mu     sync.RWMutex
-	banned map[string]struct{}
+	banned map[string]BanEntry
+	nextID uint64
 }
 
 func NewBanManager() *BanManager {
-	return &BanManager{banned: make(map[string]struct{})}
+	return &BanManager{banned: make(map[string]BanEntry)}
 }
 
 func (b *BanManager) IsBanned(ip string) bool {
+	_, ok := b.Lookup(ip)
+	return ok
+}
+
+// Lookup returns the ban entry for ip, if it is banned.
+func (b *BanManager) Lookup(ip string) (BanEntry, bool) {
 	b.mu.RLock()
-	_, ok := b.banned[ip]
+	entry, ok := b.banned[ip]
 	b.mu.RUnlock()
-	return ok
+	if ok && !entry.Expires.IsZero() && !time.Now().Before(entry.Expires) {
+		return BanEntry{}, false
+	}
+	return entry, ok
+}
+
+// Cleanup forgets temporary bans that have expired and returns how many were
+// removed.
+func (b *BanManager) Cleanup() int {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	now := time.Now()
+	removed := 0
+	for ip, entry := range b.banned {
+		if !entry.Expires.IsZero() && !now.Before(entry.Expires) {
+			delete(b.banned, ip)
+			removed++
+		}
+	}
+	return removed
 }
 
-func (b *BanManager) Ban(ip string) {
+// Ban bans entry.IP, assigning the entry an ID and time, and returns it.
+func (b *BanManager) Ban(entry BanEntry) BanEntry {
 	b.mu.Lock()
-	b.banned[ip] = struct{}{}
+	b.nextID++
+	entry.ID = b.nextID
+	if entry.Time.IsZero() {
+		entry.Time = time.Now()
+	}
+	b.banned[entry.IP] = entry
 	b.mu.Unlock()
+	log.Printf("Ban #%d: %s (%s) banned by %s: %s", entry.ID, entry.IP, entry.Nickname, entry.IssuedBy, entry.Reason)
+	return entry
 }
 
 var banManager = NewBanManager()
@@ -70,11 +143,14 @@ var banManager = NewBanManager()
 type ConnectionRateLimiter struct {
 	mu      sync.Mutex
 	entries map[string][]time.Time
+	limit   int
 }
 
-func NewConnectionRateLimiter() *ConnectionRateLimiter {
+// NewConnectionRateLimiter allows up to limit connections per IP per minute.
+func NewConnectionRateLimiter(limit int) *ConnectionRateLimiter {
 	return &ConnectionRateLimiter{
 		entries: make(map[string][]time.Time),
+		limit:   limit,
 	}
 }
 
@@ -95,7 +171,7 @@ func (rl *ConnectionRateLimiter) CheckAndRecord(ip string) bool {
 		}
 	}
 
-	if len(newTimestamps) >= 5 {
+	if len(newTimestamps) >= rl.limit {
 		return false
 	}
 
@@ -104,9 +180,66 @@ func (rl *ConnectionRateLimiter) CheckAndRecord(ip string) bool {
 	return true
 }
 
+// Cleanup forgets IPs with no connections in the last minute and returns how
+// many were removed.
+func (rl *ConnectionRateLimiter) Cleanup() int {
+	rl.mu.Lock()
+	defer rl.mu.Unlock()
+
+	oneMinuteAgo := time.Now().Add(-1 * time.Minute)
+	removed := 0
+	for ip, timestamps := range rl.entries {
+		// Timestamps are appended in order, so the last one is the newest
+		if len(timestamps) == 0 || !timestamps[len(timestamps)-1].After(oneMinuteAgo) {
+			delete(rl.entries, ip)
+			removed++
+		}
+	}
+	return removed
+}
+
+// runMaintenance periodically prunes stale per-IP connection records, expired
+// bans, /votekick cooldowns and mutes so they don't grow without bound.
+func runMaintenance(interval time.Duration) {
+	ticker := time.NewTicker(interval)
+	defer ticker.Stop()
+	for range ticker.C {
+		removed := rateLimiter.Cleanup() + attemptLimiter.Cleanup()
+		if removed > 0 {
+			log.Printf("Maintenance: pruned %d stale connection record(s).", removed)
+		}
+		if expired := banManager.Cleanup(); expired > 0 {
+			log.Printf("Maintenance: pruned %d expired ban(s).", expired)
+		}
+		if expired := globalChat.PruneVoteKickCooldowns(); expired > 0 {
+			log.Printf("Maintenance: pruned %d expired /votekick cooldown(s).", expired)
+		}
+		if expired := globalChat.PruneMutes(); expired > 0 {
+			log.Printf("Maintenance: pruned %d expired mute(s).", expired)
+		}
+	}
+}
+
+// prerender formats the parts of msg that look the same for every client so
+// that render doesn't rebuild them per client on every frame.
+func (m *Message) prerender() {
+	color := m.Color
+	if color == 0 {
+		color = 37 // default to white
+	}
+	m.coloredNick = fmt.Sprintf("\x1b[%dm%s\x1b[0m", color, m.Nick)
+
+	// Highlight mentions in the message text
+	m.highlighted = highlightMentions(m.Text, m.Mentions)
+}
+
 func NewChatServer() *ChatServer {
 	cs := &ChatServer{
-		clients: make(map[*Client]struct{}),
+		clients:      make(map[*Client]struct{}),
+		lastVoteKick: make(map[string]time.Time),
+		muted:        make(map[string]time.Time),
+		byIP:         make(map[string]map[*Client]struct{}),
+		bySkeleton:   make(map[string]*Client),
 	}
 	welcome := Message{
 		Time:  time.Now(),
@@ -114,26 +247,50 @@ func NewChatServer() *ChatServer {
 		Text:  "Welcome to the SSH chat! Use ↑/↓ to scroll and Enter to send messages.",
 		Color: 37,
 	}
+	welcome.prerender()
 	cs.messages = append(cs.messages, welcome)
 	cs.logMessage(welcome)
 	return cs
 }
 
-func (cs *ChatServer) AddClient(c *Client) {
+// AddClient registers c unless its nickname looks like that of a client
+// already connected.
+func (cs *ChatServer) AddClient(c *Client) error {
+	skeleton := nicknameSkeleton(c.nickname)
 	cs.mu.Lock()
+	defer cs.mu.Unlock()
+	if other, ok := cs.bySkeleton[skeleton]; ok {
+		return fmt.Errorf("nickname %q is too similar to %q, who is already connected", c.nickname, other.nickname)
+	}
 	cs.clients[c] = struct{}{}
-	cs.mu.Unlock()
+	cs.bySkeleton[skeleton] = c
+	if cs.byIP[c.ip] == nil {
+		cs.byIP[c.ip] = make(map[*Client]struct{})
+	}
+	cs.byIP[c.ip][c] = struct{}{}
+	return nil
 }
 
 func (cs *ChatServer) RemoveClient(c *Client) {
+	skeleton := nicknameSkeleton(c.nickname)
 	cs.mu.Lock()
 	delete(cs.clients, c)
+	if cs.bySkeleton[skeleton] == c {
+		delete(cs.bySkeleton, skeleton)
+	}
+	if sameIP, ok := cs.byIP[c.ip]; ok {
+		delete(sameIP, c)
+		if len(sameIP) == 0 {
+			delete(cs.byIP, c.ip)
+		}
+	}
 	cs.mu.Unlock()
 }
 
 func (cs *ChatServer) AppendMessage(msg Message) {
 	// Detect mentions in the message
 	msg.Mentions = extractMentions(msg.Text)
+	msg.prerender()
 
 	cs.mu.Lock()
 	cs.messages = append(cs.messages, msg)
@@ -167,14 +324,272 @@ func (cs *ChatServer) AppendSystemMessage(text string) {
 	})
 }
 
+// Mute keeps clients from ip from posting for d. It outlasts reconnects.
+func (cs *ChatServer) Mute(ip string, d time.Duration) {
+	cs.mu.Lock()
+	cs.muted[ip] = time.Now().Add(d)
+	cs.mu.Unlock()
+}
+
+// MutedFor returns how much longer clients from ip are muted, or zero.
+func (cs *ChatServer) MutedFor(ip string) time.Duration {
+	cs.mu.RLock()
+	until, ok := cs.muted[ip]
+	cs.mu.RUnlock()
+	if !ok {
+		return 0
+	}
+	return max(time.Until(until), 0)
+}
+
+// PruneMutes forgets mutes that have ended and returns how many were removed.
+func (cs *ChatServer) PruneMutes() int {
+	cs.mu.Lock()
+	defer cs.mu.Unlock()
+	removed := 0
+	now := time.Now()
+	for ip, until := range cs.muted {
+		if !now.Before(until) {
+			delete(cs.muted, ip)
+			removed++
+		}
+	}
+	return removed
+}
+
+// Once a join/leave notice is posted, further ones within presenceWindow are
+// collected and posted as a single summary, so mass joins and reconnect loops
+// can't flood everyone's screen.
+const presenceWindow = 10 * time.Second
+
+type presenceBatch struct {
+	joined []string
+	left   []string
+}
+
+// AnnouncePresence posts "<nickname> joined/left the chat", or queues it for
+// the next summary if another notice was posted within presenceWindow.
+func (cs *ChatServer) AnnouncePresence(nickname string, joined bool) {
+	cs.mu.Lock()
+	if cs.presencePending == nil && time.Since(cs.presenceAt) >= presenceWindow {
+		cs.presenceAt = time.Now()
+		cs.mu.Unlock()
+		if joined {
+			cs.AppendSystemMessage(fmt.Sprintf("%s joined the chat", nickname))
+		} else {
+			cs.AppendSystemMessage(fmt.Sprintf("%s left the chat", nickname))
+		}
+		return
+	}
+	if cs.presencePending == nil {
+		cs.presencePending = &presenceBatch{}
+		time.AfterFunc(presenceWindow-time.Since(cs.presenceAt), cs.flushPresence)
+	}
+	if joined {
+		cs.presencePending.joined = append(cs.presencePending.joined, nickname)
+	} else {
+		cs.presencePending.left = append(cs.presencePending.left, nickname)
+	}
+	cs.mu.Unlock()
+}
+
+func (cs *ChatServer) flushPresence() {
+	cs.mu.Lock()
+	batch := cs.presencePending
+	cs.presencePending = nil
+	cs.presenceAt = time.Now()
+	cs.mu.Unlock()
+	if batch == nil {
+		return
+	}
+
+	var parts []string
+	if len(batch.joined) > 0 {
+		parts = append(parts, summarizeNicknames(batch.joined)+" joined the chat")
+	}
+	if len(batch.left) > 0 {
+		parts = append(parts, summarizeNicknames(batch.left)+" left the chat")
+	}
+	cs.AppendSystemMessage(strings.Join(parts, ", "))
+}
+
+// summarizeNicknames renders "alice", "3 users (alice, bob, carol)" or
+// "12 users", counting each nickname once.
+func summarizeNicknames(nicknames []string) string {
+	seen := make(map[string]struct{}, len(nicknames))
+	unique := make([]string, 0, len(nicknames))
+	for _, n := range nicknames {
+		if _, ok := seen[n]; !ok {
+			seen[n] = struct{}{}
+			unique = append(unique, n)
+		}
+	}
+	switch {
+	case len(unique) == 1:
+		return unique[0]
+	case len(unique) <= 5:
+		return fmt.Sprintf("%d users (%s)", len(unique), strings.Join(unique, ", "))
+	default:
+		return fmt.Sprintf("%d users", len(unique))
+	}
+}
+
+// ClientByNickname returns the connected client whose nickname matches, or
+// looks like, nickname. It returns nil if there is none.
+func (cs *ChatServer) ClientByNickname(nickname string) *Client {
+	cs.mu.RLock()
+	defer cs.mu.RUnlock()
+	return cs.bySkeleton[nicknameSkeleton(nickname)]
+}
+
+// A /votekick poll stays open for voteKickDuration and passes with at least
+// voteKickQuorum yes votes outnumbering the no votes, banning the target's IP
+// for voteKickBanDuration. Each IP may start one poll per voteKickCooldown.
+const (
+	voteKickDuration    = 60 * time.Second
+	voteKickQuorum      = 3
+	voteKickCooldown    = 5 * time.Minute
+	voteKickBanDuration = 15 * time.Minute
+)
+
+// kickVote is the open /votekick poll. Votes are keyed by IP so one person
+// with several sessions counts once.
+type kickVote struct {
+	target *Client
+	votes  map[string]bool
+}
+
+// tally counts the votes and reports whether the kick passed.
+func (v *kickVote) tally() (yes, no int, passed bool) {
+	for _, voted := range v.votes {
+		if voted {
+			yes++
+		} else {
+			no++
+		}
+	}
+	return yes, no, yes >= voteKickQuorum && yes > no
+}
+
+// StartVoteKick opens a poll to kick the client named nickname, with the
+// initiator's vote counted as yes.
+func (cs *ChatServer) StartVoteKick(initiator *Client, nickname string) error {
+	target := cs.ClientByNickname(nickname)
+	if target == nil {
+		return fmt.Errorf("no user named %q", nickname)
+	}
+	if target == initiator {
+		return errors.New("you can't vote to kick yourself")
+	}
+
+	cs.mu.Lock()
+	if cs.vote != nil {
+		cs.mu.Unlock()
+		return errors.New("a vote is already in progress")
+	}
+	if last, ok := cs.lastVoteKick[initiator.ip]; ok && time.Since(last) < voteKickCooldown {
+		cs.mu.Unlock()
+		return fmt.Errorf("you can start another vote in %s", (voteKickCooldown - time.Since(last)).Round(time.Second))
+	}
+	cs.lastVoteKick[initiator.ip] = time.Now()
+	cs.vote = &kickVote{target: target, votes: map[string]bool{initiator.ip: true}}
+	cs.mu.Unlock()
+
+	cs.AppendSystemMessage(fmt.Sprintf("`%s` started a vote to kick `%s`. Type /vote yes or /vote no within %s.", initiator.nickname, target.nickname, voteKickDuration))
+	time.AfterFunc(voteKickDuration, cs.finishVoteKick)
+	return nil
+}
+
+// Vote records voter's answer in the open /votekick poll.
+func (cs *ChatServer) Vote(voter *Client, yes bool) error {
+	cs.mu.Lock()
+	defer cs.mu.Unlock()
+	if cs.vote == nil {
+		return errors.New("no vote in progress")
+	}
+	cs.vote.votes[voter.ip] = yes
+	return nil
+}
+
+func (cs *ChatServer) finishVoteKick() {
+	cs.mu.Lock()
+	vote := cs.vote
+	cs.vote = nil
+	var connected bool
+	if vote != nil {
+		_, connected = cs.clients[vote.target]
+	}
+	cs.mu.Unlock()
+	if vote == nil {
+		return
+	}
+	if !connected {
+		cs.AppendSystemMessage(fmt.Sprintf("Vote to kick `%s` cancelled: they already left.", vote.target.nickname))
+		return
+	}
+
+	yes, no, passed := vote.tally()
+	if !passed {
+		cs.AppendSystemMessage(fmt.Sprintf("Vote to kick `%s` failed (%d yes, %d no).", vote.target.nickname, yes, no))
+		return
+	}
+
+	banManager.Ban(BanEntry{
+		IP:       vote.target.ip,
+		Nickname: vote.target.nickname,
+		IssuedBy: "votekick",
+		Reason:   fmt.Sprintf("kicked by vote (%d yes, %d no)", yes, no),
+		Expires:  time.Now().Add(voteKickBanDuration),
+	})
+	cs.AppendSystemMessage(fmt.Sprintf("Vote passed (%d yes, %d no). `%s` kicked and banned for %d minutes.", yes, no, vote.target.nickname, int(voteKickBanDuration/time.Minute)))
+	cs.DisconnectByIP(vote.target.ip)
+}
+
+// PruneVoteKickCooldowns forgets initiators whose /votekick cooldown has
+// expired and returns how many were removed.
+func (cs *ChatServer) PruneVoteKickCooldowns() int {
+	cs.mu.Lock()
+	defer cs.mu.Unlock()
+	removed := 0
+	for ip, last := range cs.lastVoteKick {
+		if time.Since(last) >= voteKickCooldown {
+			delete(cs.lastVoteKick, ip)
+			removed++
+		}
+	}
+	return removed
+}
+
+// LastMessageByIP returns the text of the latest message in the history that
+// was posted from ip, or "" if there is none.
+func (cs *ChatServer) LastMessageByIP(ip string) string {
+	cs.mu.RLock()
+	defer cs.mu.RUnlock()
+	for i := len(cs.messages) - 1; i >= 0; i-- {
+		if cs.messages[i].IP == ip {
+			return cs.messages[i].Text
+		}
+	}
+	return ""
+}
+
+// NicknamesByIP returns the nicknames of the clients connected from ip.
+func (cs *ChatServer) NicknamesByIP(ip string) []string {
+	cs.mu.RLock()
+	defer cs.mu.RUnlock()
+	nicknames := make([]string, 0, len(cs.byIP[ip]))
+	for c := range cs.byIP[ip] {
+		nicknames = append(nicknames, c.nickname)
+	}
+	return nicknames
+}
+
 // DisconnectByIP closes all clients currently connected from the given IP.
 func (cs *ChatServer) DisconnectByIP(ip string) int {
 	cs.mu.RLock()
-	clients := make([]*Client, 0, len(cs.clients))
-	for c := range cs.clients {
-		if c.ip == ip {
-			clients = append(clients, c)
-		}
+	clients := make([]*Client, 0, len(cs.byIP[ip]))
+	for c := range cs.byIP[ip] {
+		clients = append(clients, c)
 	}
 	cs.mu.RUnlock()
 	for _, c := range clients {
@@ -220,17 +635,49 @@ type Client struct {
 	height            int
 	scrollOffset      int
 	inputBuffer       []rune
+	inputTooLong      bool // input went past maxMessageLength and was cut
 	messageTimestamps []time.Time
+	recentMessages    []recentMessage
+	duplicateTimes    []time.Time // when recent duplicates were dropped
+	notice            string
+	printed           int // plain mode: messages already written, -1 before the first render
+	location          *time.Location
+
+	updateCh    chan struct{}
+	done        chan struct{}
+	closeOnce   sync.Once
+	wg          sync.WaitGroup
+	nickname    string
+	color       int
+	ip          string
+	connectedAt time.Time
+	plain       bool // line-by-line output without ANSI escapes
+	echo        bool // plain mode: write typed input back to the terminal
+}
 
-	updateCh  chan struct{}
-	done      chan struct{}
-	closeOnce sync.Once
-	wg        sync.WaitGroup
-	nickname  string
-	color     int
-	ip        string
+// recentMessage remembers a normalized message hash for duplicate detection.
+type recentMessage struct {
+	hash uint64
+	at   time.Time
 }
 
+// A message matching one the client posted within duplicateWindow is dropped,
+// and maxDuplicates drops within duplicateOffenseWindow mute the client's IP
+// for duplicateMuteDuration.
+const (
+	duplicateWindow        = 30 * time.Second
+	duplicateOffenseWindow = 5 * time.Minute
+	maxDuplicates          = 5
+	duplicateMuteDuration  = 10 * time.Minute
+)
+
+// Clients connected for less than probationPeriod are on probation: they may
+// not post links (see checkURLPolicy) and get a lower message rate limit.
+const (
+	probationPeriod       = 5 * time.Minute
+	probationMessageLimit = 10 // messages per minute, instead of 30
+)
+
 var colors = []int{
 	31, 32, 33, 34, 35, 36,
 }
@@ -254,6 +701,9 @@ func NewClient(server *ChatServer, session ssh.Session, nickname string, width,
 		inputBuffer:       make([]rune, 0, 128),
 		messageTimestamps: make([]time.Time, 0),
 		ip:                ip,
+		connectedAt:       time.Now(),
+		printed:           -1,
+		location:          time.Local, // server zone (TZ) until the client sets /tz
 	}
 }
 
@@ -303,6 +753,20 @@ func (c *Client) NotifyWithBell(withBell bool) {
 	c.Notify()
 }
 
+// onProbation reports whether the client connected less than probationPeriod ago.
+func (c *Client) onProbation() bool {
+	return time.Since(c.connectedAt) < probationPeriod
+}
+
+// SetNotice shows text in this client's status line until the next message is
+// sent. Used to tell the sender why their message was rejected.
+func (c *Client) SetNotice(text string) {
+	c.mu.Lock()
+	c.notice = text
+	c.mu.Unlock()
+	c.Notify()
+}
+
 func (c *Client) SetWindowSize(width, height int) {
 	c.mu.Lock()
 	if width > 0 && width <= 8192 {
@@ -333,14 +797,66 @@ func (c *Client) renderLoop() {
 	}
 }
 
+// EnablePlainMode switches the client to line-by-line output without cursor
+// movement or colors, for terminals that can't handle them. With echo set the
+// server writes typed input back, as the terminal won't. Call before Start.
+func (c *Client) EnablePlainMode(echo bool) {
+	c.plain = true
+	c.echo = echo
+}
+
+// renderPlain writes messages that arrived since the last call as plain text
+// lines, followed by any pending notice.
+func (c *Client) renderPlain() {
+	allMessages := c.server.Messages()
+
+	c.mu.Lock()
+	start := c.printed
+	c.printed = len(allMessages)
+	notice := c.notice
+	c.notice = ""
+	loc := c.location
+	c.mu.Unlock()
+
+	if start < 0 {
+		// First render: show only the latest part of the history
+		start = max(len(allMessages)-20, 0)
+	}
+	if start > len(allMessages) {
+		start = len(allMessages)
+	}
+
+	var b strings.Builder
+	for _, msg := range allMessages[start:] {
+		text := strings.ReplaceAll(msg.Text, "\n", "\r\n    ")
+		fmt.Fprintf(&b, "[%s] %s: %s\r\n", msg.Time.In(loc).Format("15:04:05"), msg.Nick, text)
+	}
+	if notice != "" {
+		fmt.Fprintf(&b, "* %s\r\n", notice)
+	}
+	if b.Len() == 0 {
+		return
+	}
+	if _, err := c.session.Write([]byte(b.String())); err != nil {
+		c.Close()
+	}
+}
+
 func (c *Client) render() {
+	if c.plain {
+		c.renderPlain()
+		return
+	}
+
 	allMessages := c.server.Messages()
 
 	c.mu.Lock()
 	width := c.width
 	height := c.height
+	loc := c.location
 	scroll := c.scrollOffset
 	inputCopy := append([]rune(nil), c.inputBuffer...)
+	notice := c.notice
 	c.mu.Unlock()
 
 	if width <= 0 {
@@ -365,7 +881,7 @@ func (c *Client) render() {
 	for i := len(allMessages) - 1; i >= 0; i-- {
 		msg := allMessages[i]
 		// 메시지 하나를 포맷팅하여 라인들로 변환합니다.
-		msgLines := formatMessage(msg, width)
+		msgLines := formatMessage(msg, width, loc)
 
 		// 생성된 라인들을 `relevantLines`의 앞쪽에 추가합니다.
 		// 이렇게 하면 메시지 순서가 올바르게 유지됩니다.
@@ -404,14 +920,18 @@ func (c *Client) render() {
 	displayLines := relevantLines[start:end]
 
 	status := fmt.Sprintf("Users:%d Messages:%d Scroll:%d/%d ↑/↓ to scroll", c.server.ClientCount(), len(allMessages), scroll, maxOffset)
-	status = fitString(status, width)
+	status = truncateWidth(status, width)
+	if notice != "" {
+		// 거절 사유 등은 보낸 사람의 상태줄에만 빨간색으로 표시합니다.
+		status = fmt.Sprintf("\x1b[1;31m%s\x1b[0m", truncateWidth(notice, width))
+	}
 
 	inputText := string(inputCopy)
 	inputLimit := width - 2
 	if inputLimit < 1 {
 		inputLimit = width
 	}
-	inputText = tailString(inputText, inputLimit)
+	inputText = tailWidth(inputText, inputLimit)
 
 	var b strings.Builder
 	b.Grow((messageArea + 3) * (width + 8))
@@ -453,7 +973,12 @@ func (c *Client) inputLoop(reader *bufio.Reader) {
 		case '\r':
 			c.handleEnter()
 		case '\n':
-			// ignore bare line feeds; carriage return already handled
+			// Plain mode without echo means there is no PTY, so lines end
+			// with a bare line feed; otherwise carriage return already
+			// handled it
+			if c.plain && !c.echo {
+				c.handleEnter()
+			}
 		case 127, '\b':
 			c.handleBackspace()
 		case 3: // Ctrl+C
@@ -473,18 +998,37 @@ func (c *Client) inputLoop(reader *bufio.Reader) {
 }
 
 func (c *Client) handleEnter() {
+	if c.echo {
+		c.session.Write([]byte("\r\n"))
+	}
+
 	c.mu.Lock()
 	text := strings.TrimSpace(string(c.inputBuffer))
+	tooLong := c.inputTooLong
 	c.inputBuffer = c.inputBuffer[:0]
+	c.inputTooLong = false
 	c.scrollOffset = 0
+	c.notice = ""
 	c.mu.Unlock()
 	c.Notify()
 
+	if tooLong {
+		c.SetNotice(fmt.Sprintf("%v (max %d characters), not sent", ErrMessageTooLong, maxMessageLength))
+		return
+	}
+
 	if text == "" {
 		return
 	}
+	text = collapseRepeatedRuns(text, maxRepeatedRunes)
 
-	if err := ValidateNoCombining(text); err != nil {
+	if err := ValidateMessage(text); err != nil {
+		c.SetNotice(err.Error())
+		return
+	}
+
+	if left := c.server.MutedFor(c.ip); left > 0 {
+		c.SetNotice(fmt.Sprintf("You are muted for repeating messages (%s left)", left.Round(time.Second)))
 		return
 	}
 
@@ -505,11 +1049,46 @@ func (c *Client) handleEnter() {
 	// Add current message timestamp
 	c.messageTimestamps = append(c.messageTimestamps, now)
 	messageCount := len(c.messageTimestamps)
+
+	// Reject messages identical (ignoring case, spacing and punctuation) to one
+	// posted within duplicateWindow. Hashes are only recorded once a message
+	// is actually posted, so a message refused for another reason can be
+	// sent again.
+	hash := messageHash(text)
+	duplicate := false
+	n = 0
+	for _, rm := range c.recentMessages {
+		if now.Sub(rm.at) < duplicateWindow {
+			c.recentMessages[n] = rm
+			n++
+			if rm.hash == hash {
+				duplicate = true
+			}
+		}
+	}
+	c.recentMessages = c.recentMessages[:n]
+	duplicates := 0
+	if duplicate {
+		n = 0
+		for _, ts := range c.duplicateTimes {
+			if now.Sub(ts) < duplicateOffenseWindow {
+				c.duplicateTimes[n] = ts
+				n++
+			}
+		}
+		c.duplicateTimes = append(c.duplicateTimes[:n], now)
+		duplicates = len(c.duplicateTimes)
+	}
 	c.mu.Unlock()
 
 	if messageCount > 30 {
-		log.Printf("Kicking client %s (%s) for spamming.", c.nickname, c.ip)
-		banManager.Ban(c.ip)
+		banManager.Ban(BanEntry{
+			IP:       c.ip,
+			Nickname: c.nickname,
+			IssuedBy: "spam filter",
+			Reason:   "more than 30 messages per minute",
+			Message:  text,
+		})
 		msg := fmt.Sprintf("야 `%s` 나가.", c.nickname)
 		c.server.AppendSystemMessage(msg)
 		c.session.Exit(1)
@@ -517,19 +1096,87 @@ func (c *Client) handleEnter() {
 		return
 	}
 
+	if messageCount > probationMessageLimit && c.onProbation() {
+		c.SetNotice(fmt.Sprintf("New users may send at most %d messages per minute", probationMessageLimit))
+		return
+	}
+
+	if duplicate {
+		if duplicates >= maxDuplicates {
+			c.mu.Lock()
+			c.duplicateTimes = nil
+			c.mu.Unlock()
+			c.server.Mute(c.ip, duplicateMuteDuration)
+			log.Printf("Muting %s (%s) for repeating messages.", c.nickname, c.ip)
+			c.server.AppendSystemMessage(fmt.Sprintf("`%s` muted for %d minutes for repeating the same message.", c.nickname, int(duplicateMuteDuration/time.Minute)))
+			return
+		}
+		c.SetNotice(fmt.Sprintf("Message dropped: you sent the same message less than %s ago", duplicateWindow))
+		return
+	}
+
+	if err := checkURLPolicy(text, time.Since(c.connectedAt)); err != nil {
+		c.SetNotice(err.Error())
+		return
+	}
+
 	// Commands
 	if strings.HasPrefix(text, "/ban ") {
-		target := strings.TrimSpace(strings.TrimPrefix(text, "/ban "))
+		// /ban <ip> [reason]
+		args := strings.Fields(strings.TrimPrefix(text, "/ban "))
 		// Allow just IP (IPv4/IPv6). No CIDR support for simplicity.
-		if ip := net.ParseIP(target); ip == nil {
+		var ip net.IP
+		if len(args) > 0 {
+			ip = net.ParseIP(args[0])
+		}
+		if ip == nil {
 			c.server.AppendSystemMessage("Invalid IP address")
 			return
 		}
-		banManager.Ban(target)
+		target := ip.String()
+		reason := strings.Join(args[1:], " ")
+		if reason == "" {
+			reason = "/ban"
+		}
+		banManager.Ban(BanEntry{
+			IP:       target,
+			Nickname: strings.Join(c.server.NicknamesByIP(target), ", "),
+			IssuedBy: c.nickname,
+			Reason:   reason,
+			Message:  c.server.LastMessageByIP(target),
+		})
 		disconnected := c.server.DisconnectByIP(target)
 		c.server.AppendSystemMessage(fmt.Sprintf("IP %s banned. Disconnected %d session(s).", target, disconnected))
 		return
 	}
+	if strings.HasPrefix(text, "/tz ") {
+		name := strings.TrimSpace(strings.TrimPrefix(text, "/tz "))
+		loc, err := time.LoadLocation(name)
+		if err != nil {
+			c.SetNotice(fmt.Sprintf("Unknown time zone %q (use an IANA name like Asia/Seoul)", name))
+			return
+		}
+		c.mu.Lock()
+		c.location = loc
+		c.mu.Unlock()
+		c.SetNotice(fmt.Sprintf("Timestamps are now shown in %s", loc))
+		return
+	}
+	if strings.HasPrefix(text, "/votekick ") {
+		target := strings.TrimPrefix(strings.TrimSpace(strings.TrimPrefix(text, "/votekick ")), "@")
+		if err := c.server.StartVoteKick(c, target); err != nil {
+			c.SetNotice(err.Error())
+		}
+		return
+	}
+	if text == "/vote yes" || text == "/vote no" {
+		if err := c.server.Vote(c, text == "/vote yes"); err != nil {
+			c.SetNotice(err.Error())
+		} else {
+			c.SetNotice("Vote recorded.")
+		}
+		return
+	}
 
 	c.server.AppendMessage(Message{
 		Time:  time.Now(),
@@ -538,6 +1185,9 @@ func (c *Client) handleEnter() {
 		Color: c.color,
 		IP:    c.ip,
 	})
+	c.mu.Lock()
+	c.recentMessages = append(c.recentMessages, recentMessage{hash: hash, at: time.Now()})
+	c.mu.Unlock()
 
 	if strings.Contains(text, "rm -") {
 		c.server.AppendSystemMessage("이거 리눅스아니에요. 윈도 파워쉘요.")
@@ -578,17 +1228,33 @@ func (c *Client) handleEnter() {
 
 func (c *Client) handleBackspace() {
 	c.mu.Lock()
-	if len(c.inputBuffer) > 0 {
+	removed := len(c.inputBuffer) > 0
+	if removed {
 		c.inputBuffer = c.inputBuffer[:len(c.inputBuffer)-1]
+		// What is left is on screen, so it may be sent as it is
+		c.inputTooLong = false
 	}
 	c.mu.Unlock()
+	if removed && c.echo {
+		c.session.Write([]byte("\b \b"))
+	}
 	c.Notify()
 }
 
 func (c *Client) handleRune(r rune) {
 	c.mu.Lock()
-	c.inputBuffer = append(c.inputBuffer, r)
+	accepted := len(c.inputBuffer) < maxMessageLength
+	if accepted {
+		c.inputBuffer = append(c.inputBuffer, r)
+	} else if !c.inputTooLong {
+		// Rejected on Enter; warn now so the user can shorten it
+		c.inputTooLong = true
+		c.notice = fmt.Sprintf("%v (max %d characters)", ErrMessageTooLong, maxMessageLength)
+	}
 	c.mu.Unlock()
+	if accepted && c.echo {
+		c.session.Write([]byte(string(r)))
+	}
 	c.Notify()
 }
 
@@ -622,23 +1288,132 @@ func (c *Client) handleEscape(reader *bufio.Reader) {
 	}
 }
 
+// Link posting policy: links to urlAllowedDomains (and their subdomains) are
+// always allowed; other links only from clients connected for at least
+// urlMinConnectedAge, or never when blockAllURLs is set. See loadURLPolicy.
+var (
+	blockAllURLs       = false
+	urlMinConnectedAge = probationPeriod
+	urlAllowedDomains  = []string{"github.com", "go.dev"}
+)
+
+// loadURLPolicy reads the link policy from the environment:
+// SSH_CHAT_BLOCK_URLS=true blocks links outside the allow-list entirely, and
+// SSH_CHAT_URL_ALLOWLIST=example.com,example.org replaces the allow-list
+// (empty allows no domain).
+func loadURLPolicy() {
+	if v := os.Getenv("SSH_CHAT_BLOCK_URLS"); v != "" {
+		block, err := strconv.ParseBool(v)
+		if err != nil {
+			log.Printf("Ignoring SSH_CHAT_BLOCK_URLS=%q: %v", v, err)
+		} else {
+			blockAllURLs = block
+		}
+	}
+	if v, ok := os.LookupEnv("SSH_CHAT_URL_ALLOWLIST"); ok {
+		urlAllowedDomains = nil
+		for _, d := range strings.Split(v, ",") {
+			if d = strings.ToLower(strings.TrimSpace(d)); d != "" {
+				urlAllowedDomains = append(urlAllowedDomains, d)
+			}
+		}
+	}
+}
+
+// checkURLPolicy returns the reason a message with links must be rejected for
+// a client that has been connected for the given duration, or nil.
+func checkURLPolicy(text string, connected time.Duration) error {
+	for _, host := range extractURLHosts(text) {
+		if isAllowedDomain(host) {
+			continue
+		}
+		if blockAllURLs {
+			return fmt.Errorf("links to %s are not allowed", host)
+		}
+		if connected < urlMinConnectedAge {
+			return fmt.Errorf("links are allowed after %s connected", urlMinConnectedAge)
+		}
+	}
+	return nil
+}
+
+// extractURLHosts returns the lowercased host of every http(s):// or www. link
+// in text.
+func extractURLHosts(text string) []string {
+	var hosts []string
+	for _, word := range strings.Fields(text) {
+		lower := strings.ToLower(word)
+		idx := strings.Index(lower, "http://")
+		if i := strings.Index(lower, "https://"); i != -1 && (idx == -1 || i < idx) {
+			idx = i
+		}
+		if idx == -1 {
+			idx = strings.Index(lower, "www.")
+			if idx == -1 {
+				continue
+			}
+			// Only at the start of a word, so "awww..." isn't a link
+			if r, _ := utf8.DecodeLastRuneInString(lower[:idx]); idx > 0 && (unicode.IsLetter(r) || unicode.IsDigit(r)) {
+				continue
+			}
+			lower = "http://" + lower[idx:]
+			idx = 0
+		}
+		u, err := url.Parse(strings.TrimRight(lower[idx:], ".,!?:;)"))
+		if err != nil || u.Hostname() == "" {
+			continue
+		}
+		hosts = append(hosts, u.Hostname())
+	}
+	return hosts
+}
+
+func isAllowedDomain(host string) bool {
+	for _, d := range urlAllowedDomains {
+		if host == d || strings.HasSuffix(host, "."+d) {
+			return true
+		}
+	}
+	return false
+}
+
+// messageHash hashes text with case, whitespace and punctuation removed so
+// that near-identical repeats ("spam!!", "SPAM") collide.
+func messageHash(text string) uint64 {
+	h := fnv.New64a()
+	written := false
+	for _, r := range strings.ToLower(text) {
+		if unicode.IsLetter(r) || unicode.IsDigit(r) {
+			h.Write([]byte(string(r)))
+			written = true
+		}
+	}
+	if !written {
+		// Punctuation-only messages are compared verbatim
+		h.Write([]byte(text))
+	}
+	return h.Sum64()
+}
+
+// isDumbTerminal reports whether a PTY's TERM can't handle the full-screen
+// view (cursor movement, colors), so the client should use plain mode.
+func isDumbTerminal(term string) bool {
+	return term == "" || term == "dumb"
+}
+
 func isControlRune(r rune) bool {
 	return r < 32 || r == 127
 }
 
 // [HELPER] O(n) 로직을 분리하기 위해, 메시지 '하나'만 포맷하는 헬퍼 함수를 만들었습니다.
-func formatMessage(msg Message, width int) []string {
-	color := msg.Color
-	if color == 0 {
-		color = 37 // default to white
+func formatMessage(msg Message, width int, loc *time.Location) []string {
+	if msg.coloredNick == "" {
+		msg.prerender()
 	}
-	coloredNick := fmt.Sprintf("\x1b[%dm%s\x1b[0m", color, msg.Nick)
-
-	// Highlight mentions in the message text
-	highlightedText := highlightMentions(msg.Text, msg.Mentions)
+	highlightedText := msg.highlighted
 
-	prefix := fmt.Sprintf("[%s] %s: ", msg.Time.Format("15:04:05"), coloredNick)
-	indent := strings.Repeat(" ", len(msg.Nick)+13)
+	prefix := "[" + msg.Time.In(loc).Format("15:04:05") + "] " + msg.coloredNick + ": "
+	indent := strings.Repeat(" ", stringWidth(msg.Nick)+13)
 
 	var lines []string
 	segments := strings.Split(highlightedText, "\n")
@@ -678,15 +1453,17 @@ func wrapString(s string, width int) []string {
 				inEscape = true
 			}
 			if !inEscape {
-				currentWidth++
+				// 줄보다 넓은 글자(폭 1에서 한글 등)라도 줄마다 하나는 넣어야
+				// 루프가 진행됩니다.
+				if currentWidth > 0 && currentWidth+runeWidth(r) > width {
+					breakIndex = i
+					break
+				}
+				currentWidth += runeWidth(r)
 			}
 			if r == 'm' && inEscape {
 				inEscape = false
 			}
-			if currentWidth > width {
-				breakIndex = i
-				break
-			}
 		}
 
 		if breakIndex == -1 {
@@ -728,26 +1505,298 @@ func wrapString(s string, width int) []string {
 	return result
 }
 
-func fitString(s string, width int) string {
-	if width <= 0 {
-		return s
+// wideRanges lists the code point ranges terminals draw two columns wide:
+// Hangul Jamo, CJK through Yi, Hangul Syllables, CJK compatibility
+// ideographs and forms, fullwidth forms, common emoji and CJK extension B+.
+var wideRanges = [][2]rune{
+	{0x1100, 0x115F},
+	{0x2E80, 0xA4CF},
+	{0xAC00, 0xD7A3},
+	{0xF900, 0xFAFF},
+	{0xFE30, 0xFE4F},
+	{0xFF00, 0xFF60},
+	{0xFFE0, 0xFFE6},
+	{0x1F300, 0x1F64F},
+	{0x1F900, 0x1F9FF},
+	{0x20000, 0x3FFFD},
+}
+
+// runeWidth returns the number of terminal columns r occupies.
+func runeWidth(r rune) int {
+	if isControlRune(r) || unicode.In(r, unicode.Mn, unicode.Me, unicode.Cf) {
+		return 0
 	}
-	runes := []rune(s)
-	if len(runes) <= width {
-		return s
+	for _, wr := range wideRanges {
+		if r >= wr[0] && r <= wr[1] {
+			return 2
+		}
 	}
-	return string(runes[:width])
+	return 1
 }
 
-func tailString(s string, width int) string {
-	if width <= 0 {
-		return s
+func stringWidth(s string) int {
+	width := 0
+	for _, r := range s {
+		width += runeWidth(r)
 	}
-	runes := []rune(s)
-	if len(runes) <= width {
-		return s
+	return width
+}
+
+// truncateWidth cuts s to at most width terminal columns without splitting a
+// wide character.
+func truncateWidth(s string, width int) string {
+	used := 0
+	for i, r := range s {
+		used += runeWidth(r)
+		if used > width {
+			return s[:i]
+		}
+	}
+	return s
+}
+
+// tailWidth keeps the end of s that fits in width terminal columns without
+// splitting a wide character.
+func tailWidth(s string, width int) string {
+	used := 0
+	for i := len(s); i > 0; {
+		r, size := utf8.DecodeLastRuneInString(s[:i])
+		used += runeWidth(r)
+		if used > width {
+			return s[i:]
+		}
+		i -= size
+	}
+	return s
+}
+
+// Connections must finish the SSH handshake and open a session within
+// handshakeTimeout, and at most maxPendingHandshakes may be doing so at once.
+const (
+	handshakeTimeout     = 10 * time.Second
+	maxPendingHandshakes = 64
+)
+
+var handshakeSlots = make(chan struct{}, maxPendingHandshakes)
+
+type pendingConnKey struct{}
+
+// pendingConn holds a handshake slot until the connection opens a session or
+// closes, and closes the connection if that takes longer than
+// handshakeTimeout. A timer is used rather than a read deadline because the
+// ssh package manages the connection's deadlines itself.
+type pendingConn struct {
+	net.Conn
+	timer *time.Timer
+	once  sync.Once
+}
+
+func newPendingConn(conn net.Conn) *pendingConn {
+	c := &pendingConn{Conn: conn}
+	c.timer = time.AfterFunc(handshakeTimeout, func() {
+		log.Printf("Closing connection from %s: handshake timed out.", remoteIP(conn.RemoteAddr()))
+		_ = c.Close()
+	})
+	return c
+}
+
+// established stops the handshake timer and frees the slot.
+func (c *pendingConn) established() {
+	c.once.Do(func() {
+		c.timer.Stop()
+		<-handshakeSlots
+	})
+}
+
+func (c *pendingConn) Close() error {
+	c.established()
+	return c.Conn.Close()
+}
+
+// rejectFileTransfer tells scp/sftp users, who inevitably try the chat port,
+// what this server is instead of leaving their client hanging.
+func rejectFileTransfer(s ssh.Session) {
+	kind := "sftp"
+	if s.RawCommand() != "" {
+		kind = "scp"
+	}
+	log.Printf("Rejected %s attempt from %s.", kind, remoteIP(s.RemoteAddr()))
+	fmt.Fprintf(s.Stderr(), "This is ssh-chat, a chat server: %s and file transfers are not supported.\r\n", kind)
+	fmt.Fprint(s.Stderr(), "To chat, connect with: ssh -t <nickname>@<host> -p 2222\r\n")
+	_ = s.Exit(1)
+}
+
+// maxSessionsPerConn bounds how many session channels a single connection may
+// have open at once; each one becomes a full chat client.
+const maxSessionsPerConn = 3
+
+type sessionCountKey struct{}
+
+// limitSessions wraps the default session handler, refusing session channels
+// beyond maxSessionsPerConn on the same connection.
+func limitSessions(srv *ssh.Server, conn *gossh.ServerConn, newChan gossh.NewChannel, ctx ssh.Context) {
+	count, ok := ctx.Value(sessionCountKey{}).(*int32)
+	if !ok {
+		ssh.DefaultSessionHandler(srv, conn, newChan, ctx)
+		return
+	}
+	if atomic.AddInt32(count, 1) > maxSessionsPerConn {
+		atomic.AddInt32(count, -1)
+		log.Printf("Rejecting session from %s: too many sessions on one connection.", remoteIP(conn.RemoteAddr()))
+		_ = newChan.Reject(gossh.ResourceShortage, "too many sessions on this connection")
+		return
+	}
+	// DefaultSessionHandler returns once the session channel is closed
+	defer atomic.AddInt32(count, -1)
+	ssh.DefaultSessionHandler(srv, conn, newChan, ctx)
+}
+
+// rejectForwardChannel refuses local forwarding channels (ssh -L/-D). This is
+// a chat server, not a proxy, so attempts are logged as probes.
+func rejectForwardChannel(srv *ssh.Server, conn *gossh.ServerConn, newChan gossh.NewChannel, ctx ssh.Context) {
+	dest := "unknown"
+	switch newChan.ChannelType() {
+	case "direct-streamlocal@openssh.com":
+		// Unix socket forwarding, see OpenSSH's PROTOCOL
+		var target struct {
+			SocketPath string
+			Reserved   string
+			Reserved2  uint32
+		}
+		if err := gossh.Unmarshal(newChan.ExtraData(), &target); err == nil {
+			dest = target.SocketPath
+		}
+	default:
+		var target struct {
+			DestAddr string
+			DestPort uint32
+			OrigAddr string
+			OrigPort uint32
+		}
+		if err := gossh.Unmarshal(newChan.ExtraData(), &target); err == nil {
+			dest = net.JoinHostPort(target.DestAddr, strconv.Itoa(int(target.DestPort)))
+		}
+	}
+	log.Printf("Warning: rejected %s channel from %s to %s.", newChan.ChannelType(), remoteIP(conn.RemoteAddr()), dest)
+	_ = newChan.Reject(gossh.Prohibited, "port forwarding is not allowed")
+}
+
+// rejectForwardRequest refuses remote forwarding requests (ssh -R).
+func rejectForwardRequest(ctx ssh.Context, srv *ssh.Server, req *gossh.Request) (bool, []byte) {
+	addr := "unknown"
+	switch req.Type {
+	case "streamlocal-forward@openssh.com":
+		var bind struct {
+			SocketPath string
+		}
+		if err := gossh.Unmarshal(req.Payload, &bind); err == nil {
+			addr = bind.SocketPath
+		}
+	default:
+		var bind struct {
+			BindAddr string
+			BindPort uint32
+		}
+		if err := gossh.Unmarshal(req.Payload, &bind); err == nil {
+			addr = net.JoinHostPort(bind.BindAddr, strconv.Itoa(int(bind.BindPort)))
+		}
+	}
+	log.Printf("Warning: rejected %s request from %s for %s.", req.Type, remoteIP(ctx.RemoteAddr()), addr)
+	return false, nil
+}
+
+// remoteIP strips the port from a remote address.
+func remoteIP(addr net.Addr) string {
+	remote := addr.String()
+	if host, _, err := net.SplitHostPort(remote); err == nil {
+		return host
+	}
+	return remote
+}
+
+// reservedNicknames may not be used by anyone; "server" is the nick system
+// messages are posted under.
+var reservedNicknames = []string{"server", "admin", "system"}
+
+// nicknameBlacklist holds path.Match patterns. Their skeletons are matched
+// against the whole skeleton of every word in a nickname and of the nickname
+// with its separators removed. "admin*" catches "admin_bob" and
+// "ad.ministrator" but not "badminton".
+var nicknameBlacklist = []string{"admin*", "*admin", "moderator*", "*moderator", "operator*"}
+
+// ValidateNickname rejects reserved and blacklisted nicknames, including
+// look-alikes of them (see nicknameSkeleton). Words are split at anything
+// other than letters and digits, so "admin/" or "x.moderator" don't get
+// through either.
+func ValidateNickname(nickname string) error {
+	words := strings.FieldsFunc(nickname, func(r rune) bool {
+		return !unicode.IsLetter(r) && !unicode.IsDigit(r)
+	})
+	joined := nicknameSkeleton(strings.Join(words, ""))
+	for _, reserved := range reservedNicknames {
+		if joined == nicknameSkeleton(reserved) {
+			return fmt.Errorf("nickname %q is reserved", nickname)
+		}
+	}
+	candidates := []string{joined}
+	for _, w := range words {
+		candidates = append(candidates, nicknameSkeleton(w))
+	}
+	for _, pattern := range nicknameBlacklist {
+		for _, candidate := range candidates {
+			if ok, _ := path.Match(nicknameSkeleton(pattern), candidate); ok {
+				return fmt.Errorf("nickname %q is not allowed", nickname)
+			}
+		}
+	}
+	return nil
+}
+
+// confusables maps lowercase letters that Unicode's confusables data (UTS #39
+// confusables.txt) lists as look-alikes of a Latin letter or digit to that
+// letter. It is the subset for the Cyrillic, Greek, Armenian and IPA letters
+// used to impersonate other users; compatibility variants such as fullwidth
+// or mathematical letters are already folded by NFKC.
+var confusables = map[rune]rune{
+	// Cyrillic
+	'а': 'a', 'в': 'b', 'с': 'c', 'ԁ': 'd', 'е': 'e', 'һ': 'h', 'н': 'h', 'і': 'i',
+	'ј': 'j', 'к': 'k', 'ӏ': 'l', 'м': 'm', 'п': 'n', 'о': 'o', 'р': 'p', 'ԛ': 'q',
+	'г': 'r', 'ѕ': 's', 'т': 't', 'ѵ': 'v', 'ԝ': 'w', 'х': 'x', 'у': 'y', 'ү': 'y',
+	// Greek
+	'α': 'a', 'β': 'b', 'ϲ': 'c', 'ς': 'c', 'ε': 'e', 'η': 'h', 'ι': 'i', 'ϳ': 'j',
+	'κ': 'k', 'μ': 'm', 'ο': 'o', 'σ': 'o', 'ρ': 'p', 'τ': 't', 'υ': 'u', 'ν': 'v',
+	'ω': 'w', 'χ': 'x', 'γ': 'y', 'ζ': 'z',
+	// Armenian
+	'ց': 'g', 'հ': 'h', 'ո': 'n', 'օ': 'o', 'զ': 'q', 'ս': 'u', 'ա': 'w',
+	// Latin (IPA)
+	'ɑ': 'a', 'ɡ': 'g', 'ı': 'i', 'ɩ': 'i',
+	// Digits
+	'0': 'o', '1': 'l',
+}
+
+// nicknameSkeleton folds a nickname so that visually confusable names compare
+// equal, e.g. "аdmin" (Cyrillic а), "ＡＤＭＩＮ", "𝐚𝐝𝐦𝐢𝐧" and "admin". It
+// applies NFKC, drops invisible characters, lowercases and maps confusables.
+func nicknameSkeleton(nickname string) string {
+	var b strings.Builder
+	for _, r := range norm.NFKC.String(nickname) {
+		if unicode.Is(unicode.Cf, r) || unicode.IsSpace(r) {
+			// Zero-width and other invisible characters
+			continue
+		}
+		r = unicode.ToLower(r)
+		if folded, ok := confusables[r]; ok {
+			r = folded
+		}
+		if r == 'i' {
+			// Uppercase I looks like l but lowercases to i, so all three
+			// have to fold to the same letter
+			r = 'l'
+		}
+		b.WriteRune(r)
 	}
-	return string(runes[len(runes)-width:])
+	skeleton := strings.ReplaceAll(b.String(), "rn", "m")
+	return strings.ReplaceAll(skeleton, "vv", "w")
 }
 
 func generateGuestNickname() string {
@@ -759,10 +1808,23 @@ func main() {
 	quitCh := make(chan os.Signal, 1)
 	signal.Notify(quitCh, os.Interrupt, syscall.SIGTERM, syscall.SIGINT)
 
+	loadURLPolicy()
+
 	// ssh.Handler 그대로 사용
 	h := func(s ssh.Session) {
+		if pending, ok := s.Context().Value(pendingConnKey{}).(*pendingConn); ok {
+			pending.established()
+		}
+
+		if cmd := s.RawCommand(); cmd == "scp" || strings.HasPrefix(cmd, "scp ") {
+			rejectFileTransfer(s)
+			return
+		}
+
 		ptyReq, winCh, isPty := s.Pty()
-		if !isPty {
+		// Without a PTY only an interactive shell (ssh -T) is supported, in
+		// plain mode; commands still need the full-screen client.
+		if !isPty && s.RawCommand() != "" {
 			fmt.Fprintln(s, "Error: PTY required. Reconnect with -t option.")
 			_ = s.Exit(1)
 			return
@@ -770,21 +1832,25 @@ func main() {
 
 		reader := bufio.NewReader(s)
 
-		remote := s.RemoteAddr().String()
-		ip := remote
-		if host, _, err := net.SplitHostPort(remote); err == nil {
-			ip = host
-		}
+		ip := remoteIP(s.RemoteAddr())
 
-		if banManager.IsBanned(ip) {
-			fmt.Fprintln(s, "Your IP is banned.")
+		if entry, ok := banManager.Lookup(ip); ok {
+			if entry.Expires.IsZero() {
+				fmt.Fprintf(s, "Your IP is banned (ban #%d: %s).\n", entry.ID, entry.Reason)
+			} else {
+				fmt.Fprintf(s, "Your IP is banned for another %s (ban #%d: %s).\n", time.Until(entry.Expires).Round(time.Second), entry.ID, entry.Reason)
+			}
 			_ = s.Exit(1)
 			return
 		}
 
 		if !rateLimiter.CheckAndRecord(ip) {
-			log.Printf("Banning IP %s for too many connections.", ip)
-			banManager.Ban(ip)
+			banManager.Ban(BanEntry{
+				IP:       ip,
+				Nickname: strings.TrimSpace(s.User()),
+				IssuedBy: "connection rate limiter",
+				Reason:   fmt.Sprintf("more than %d connections per minute", rateLimiter.limit),
+			})
 			disconnected := globalChat.DisconnectByIP(ip)
 			log.Printf("Disconnected %d existing session(s) from %s.", disconnected, ip)
 			fmt.Fprintln(s, "Your IP is banned for creating too many connections.")
@@ -792,37 +1858,93 @@ func main() {
 			return
 		}
 
-		nickname := strings.TrimSpace(s.User())
+		nickname := strings.Map(func(r rune) rune {
+			if isControlRune(r) {
+				return -1
+			}
+			return r
+		}, strings.TrimSpace(s.User()))
+		nickname = truncateWidth(nickname, maxNicknameWidth)
 		if nickname == "" {
 			nickname = generateGuestNickname()
 		}
-		if len([]rune(nickname)) > 10 {
-			nickname = string([]rune(nickname)[:10])
+		if err := ValidateNickname(nickname); err != nil {
+			fmt.Fprintf(s, "%v. Reconnect with: ssh <nickname>@<host> -p 2222\n", err)
+			_ = s.Exit(1)
+			return
 		}
 
 		client := NewClient(globalChat, s, nickname, int(ptyReq.Window.Width), int(ptyReq.Window.Height), ip)
-		globalChat.AddClient(client)
+		if !isPty {
+			// The client's own terminal echoes and line-buffers input
+			client.EnablePlainMode(false)
+		} else if isDumbTerminal(ptyReq.Term) {
+			client.EnablePlainMode(true)
+		}
+		if err := globalChat.AddClient(client); err != nil {
+			fmt.Fprintf(s, "%v. Reconnect with: ssh <nickname>@<host> -p 2222\n", err)
+			_ = s.Exit(1)
+			return
+		}
 		defer func() {
 			globalChat.RemoveClient(client)
 			client.Close()
-			globalChat.AppendSystemMessage(fmt.Sprintf("%s left the chat", nickname))
+			globalChat.AnnouncePresence(nickname, false)
 		}()
 
-		fmt.Fprint(s, "\x1b[2J\x1b[H")
-		globalChat.AppendSystemMessage(fmt.Sprintf("%s joined the chat", nickname))
+		if !client.plain {
+			fmt.Fprint(s, "\x1b[2J\x1b[H")
+		}
+		globalChat.AnnouncePresence(nickname, true)
 
-		go client.MonitorWindow(winCh)
+		if isPty {
+			go client.MonitorWindow(winCh)
+		}
 		client.Start(reader, s.Context())
 		client.Wait()
 	}
 
+	// 핸드셰이크 전에 IP별 접속 시도 횟수를 제한합니다.
+	connCallback := func(ctx ssh.Context, conn net.Conn) net.Conn {
+		ip := remoteIP(conn.RemoteAddr())
+		if !attemptLimiter.CheckAndRecord(ip) {
+			log.Printf("Rejecting connection attempt from %s: too many attempts.", ip)
+			return nil
+		}
+		select {
+		case handshakeSlots <- struct{}{}:
+		default:
+			log.Printf("Rejecting connection from %s: too many pending handshakes.", ip)
+			return nil
+		}
+		pending := newPendingConn(conn)
+		ctx.SetValue(pendingConnKey{}, pending)
+		ctx.SetValue(sessionCountKey{}, new(int32))
+		return pending
+	}
+
 	// 서버를 객체로 만들어서 Close 할 수 있게
 	srv := &ssh.Server{
-		Addr:    ":2222",
-		Handler: h,
+		Addr:         ":2222",
+		Handler:      h,
+		ConnCallback: connCallback,
+		ChannelHandlers: map[string]ssh.ChannelHandler{
+			"session":                        limitSessions,
+			"direct-tcpip":                   rejectForwardChannel,
+			"direct-streamlocal@openssh.com": rejectForwardChannel,
+		},
+		SubsystemHandlers: map[string]ssh.SubsystemHandler{
+			"sftp": rejectFileTransfer,
+		},
+		RequestHandlers: map[string]ssh.RequestHandler{
+			"tcpip-forward":                   rejectForwardRequest,
+			"streamlocal-forward@openssh.com": rejectForwardRequest,
+		},
 	}
 	srv.SetOption(ssh.HostKeyFile("host.key"))
 
+	go runMaintenance(5 * time.Minute)
+
 	// 서버 실행은 고루틴에서; log.Fatal 쓰지 마세요
 	go func() {
 		log.Println("starting ssh chat server on port 2222...")
@@ -944,11 +2066,52 @@ func highlightMentions(text string, mentions []string) string {
 	return result
 }
 
+var (
+	ErrMessageTooLong = errors.New("message is too long")
+	ErrCombiningMarks = errors.New("input contains combining diacritical marks (blocked)")
+)
+
+const (
+	maxMessageLength = 500 // runes
+	maxRepeatedRunes = 20  // identical runes in a row
+	maxNicknameWidth = 10  // terminal columns
+)
+
+// ValidateMessage checks a trimmed, non-empty chat message before it is
+// broadcast and returns one of the Err* values above if it must be rejected.
+func ValidateMessage(text string) error {
+	if utf8.RuneCountInString(text) > maxMessageLength {
+		return ErrMessageTooLong
+	}
+	return ValidateNoCombining(text)
+}
+
+// collapseRepeatedRuns shortens every run of more than limit identical
+// non-space runes to limit, so "ㅋㅋㅋ…" laughter is kept but a wall of one
+// character can't flood the screen.
+func collapseRepeatedRuns(text string, limit int) string {
+	var b strings.Builder
+	var prev rune
+	run := 0
+	for _, r := range text {
+		if r == prev && !unicode.IsSpace(r) {
+			run++
+		} else {
+			prev = r
+			run = 1
+		}
+		if run <= limit {
+			b.WriteRune(r)
+		}
+	}
+	return b.String()
+}
+
 func ValidateNoCombining(input string) error {
 	// 혹시 모를 누락을 대비해 룬 단위로 다시 점검(보수적)
 	for _, r := range input {
 		if isBlockedRune(r) {
-			return errors.New("input contains combining diacritical marks (blocked)")
+			return ErrCombiningMarks
 		}
 	}
 	return nil
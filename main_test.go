@@ -0,0 +1,582 @@
+package main
+
+import (
+	"fmt"
+	"io"
+	"log"
+	"os"
+	"slices"
+	"strings"
+	"testing"
+	"time"
+)
+
+func discardLogs(tb testing.TB) {
+	log.SetOutput(io.Discard)
+	tb.Cleanup(func() { log.SetOutput(os.Stderr) })
+}
+
+func TestRuneWidth(t *testing.T) {
+	tests := []struct {
+		r    rune
+		want int
+	}{
+		{'a', 1},
+		{'한', 2},
+		{'漢', 2},
+		{'Ａ', 2},      // fullwidth A
+		{'😀', 2},      // emoji
+		{'\u0301', 0}, // combining acute accent
+		{'\u200b', 0}, // zero-width space
+		{'\x1b', 0},
+	}
+	for _, tt := range tests {
+		if got := runeWidth(tt.r); got != tt.want {
+			t.Errorf("runeWidth(%q) = %d, want %d", tt.r, got, tt.want)
+		}
+	}
+}
+
+func TestStringWidth(t *testing.T) {
+	tests := []struct {
+		s    string
+		want int
+	}{
+		{"", 0},
+		{"hello", 5},
+		{"안녕", 4},
+		{"a한b", 4},
+		{"e\u0301", 1},
+	}
+	for _, tt := range tests {
+		if got := stringWidth(tt.s); got != tt.want {
+			t.Errorf("stringWidth(%q) = %d, want %d", tt.s, got, tt.want)
+		}
+	}
+}
+
+func TestTruncateWidth(t *testing.T) {
+	tests := []struct {
+		s     string
+		width int
+		want  string
+	}{
+		{"hello", 10, "hello"},
+		{"hello", 3, "hel"},
+		{"안녕하세요", 4, "안녕"},
+		{"안녕하세요", 5, "안녕"}, // a wide rune is never split
+		{"a안녕", 2, "a"},
+		{"안녕", 1, ""},
+		{"hello", 0, ""},
+		{"Users:3 안녕하세요", 12, "Users:3 안녕"}, // status line on a narrow terminal
+	}
+	for _, tt := range tests {
+		if got := truncateWidth(tt.s, tt.width); got != tt.want {
+			t.Errorf("truncateWidth(%q, %d) = %q, want %q", tt.s, tt.width, got, tt.want)
+		}
+	}
+}
+
+func TestTailWidth(t *testing.T) {
+	tests := []struct {
+		s     string
+		width int
+		want  string
+	}{
+		{"hello", 10, "hello"},
+		{"hello", 3, "llo"},
+		{"안녕하세요", 4, "세요"},
+		{"안녕하세요", 5, "세요"}, // a wide rune is never split
+		{"a안녕", 3, "녕"},
+		{"안녕", 1, ""},
+		{"", 3, ""},
+	}
+	for _, tt := range tests {
+		if got := tailWidth(tt.s, tt.width); got != tt.want {
+			t.Errorf("tailWidth(%q, %d) = %q, want %q", tt.s, tt.width, got, tt.want)
+		}
+	}
+}
+
+func TestWrapString(t *testing.T) {
+	tests := []struct {
+		s     string
+		width int
+		want  []string
+	}{
+		{"", 10, []string{""}},
+		{"hello", 10, []string{"hello"}},
+		{"abcd", 2, []string{"ab", "cd"}},
+		{"abc", 1, []string{"a", "b", "c"}},
+		{"안녕", 1, []string{"안", "녕"}}, // wider than the line
+		{"안녕", 2, []string{"안", "녕"}},
+		{"a안b", 2, []string{"a", "안", "b"}}, // wide rune at a line boundary
+		{"ab안", 3, []string{"ab", "안"}},
+		{"\x1b[31mab\x1b[0mcd", 2, []string{"\x1b[31mab\x1b[0m", "cd"}}, // escapes take no columns
+		{"\x1b[1;33m안녕\x1b[0m", 1, []string{"\x1b[1;33m안", "녕\x1b[0m"}},
+	}
+	for _, tt := range tests {
+		if got := wrapString(tt.s, tt.width); !slices.Equal(got, tt.want) {
+			t.Errorf("wrapString(%q, %d) = %q, want %q", tt.s, tt.width, got, tt.want)
+		}
+	}
+}
+
+func TestMessageHash(t *testing.T) {
+	tests := []struct {
+		a, b string
+		same bool
+	}{
+		{"hello world", "Hello World", true},
+		{"spam!!!", "SPAM", true},
+		{"buy now", "buy   now.", true},
+		{"안녕하세요", "안녕하세요!!", true},
+		{"hello", "hallo", false},
+		{"abc", "ab c d", false},
+		// Punctuation-only messages are compared verbatim
+		{"!!!", "!!!", true},
+		{"!!!", "???", false},
+		{"!!!", "!!", false},
+	}
+	for _, tt := range tests {
+		if got := messageHash(tt.a) == messageHash(tt.b); got != tt.same {
+			t.Errorf("messageHash(%q) == messageHash(%q) is %t, want %t", tt.a, tt.b, got, tt.same)
+		}
+	}
+}
+
+func TestCollapseRepeatedRuns(t *testing.T) {
+	tests := []struct {
+		text string
+		want string
+	}{
+		{"hello", "hello"},
+		{strings.Repeat("ㅋ", 20), strings.Repeat("ㅋ", 20)},
+		{strings.Repeat("ㅋ", 35), strings.Repeat("ㅋ", 20)},
+		{"와" + strings.Repeat("!", 30) + " 진짜", "와" + strings.Repeat("!", 20) + " 진짜"},
+		{strings.Repeat("ㅋ", 25) + strings.Repeat("ㅎ", 25), strings.Repeat("ㅋ", 20) + strings.Repeat("ㅎ", 20)},
+		{strings.Repeat("ab", 30), strings.Repeat("ab", 30)},
+		{"a" + strings.Repeat(" ", 30) + "b", "a" + strings.Repeat(" ", 30) + "b"}, // spaces are left alone
+	}
+	for _, tt := range tests {
+		if got := collapseRepeatedRuns(tt.text, 20); got != tt.want {
+			t.Errorf("collapseRepeatedRuns(%q) = %q, want %q", tt.text, got, tt.want)
+		}
+	}
+}
+
+func TestValidateMessage(t *testing.T) {
+	tests := []struct {
+		text string
+		want error
+	}{
+		{"안녕하세요 hello", nil},
+		{strings.Repeat("ㅋ", maxMessageLength), nil},
+		{strings.Repeat("ㅋ", maxMessageLength+1), ErrMessageTooLong},
+		{"zalgo\u0301", ErrCombiningMarks},
+	}
+	for _, tt := range tests {
+		if got := ValidateMessage(tt.text); got != tt.want {
+			t.Errorf("ValidateMessage(%q) = %v, want %v", tt.text, got, tt.want)
+		}
+	}
+}
+
+func TestExtractURLHosts(t *testing.T) {
+	tests := []struct {
+		text string
+		want []string
+	}{
+		{"no links here", nil},
+		{"see https://go.dev/doc", []string{"go.dev"}},
+		{"HTTPS://Example.COM!", []string{"example.com"}},
+		{"(https://go.dev/doc).", []string{"go.dev"}}, // trailing punctuation
+		{"https://github.com.evil.com/login", []string{"github.com.evil.com"}},
+		{"http://github.com@evil.com", []string{"evil.com"}},
+		{"www.example.com", []string{"www.example.com"}},
+		{"awww.. so cute", nil}, // www. inside a word
+		{"http:// alone", nil},
+		{"a http://a.com b https://b.com", []string{"a.com", "b.com"}},
+	}
+	for _, tt := range tests {
+		if got := extractURLHosts(tt.text); !slices.Equal(got, tt.want) {
+			t.Errorf("extractURLHosts(%q) = %q, want %q", tt.text, got, tt.want)
+		}
+	}
+}
+
+func TestIsAllowedDomain(t *testing.T) {
+	tests := []struct {
+		host string
+		want bool
+	}{
+		{"github.com", true},
+		{"gist.github.com", true},
+		{"github.com.evil.com", false},
+		{"evilgithub.com", false},
+		{"evil.com", false},
+	}
+	for _, tt := range tests {
+		if got := isAllowedDomain(tt.host); got != tt.want {
+			t.Errorf("isAllowedDomain(%q) = %t, want %t", tt.host, got, tt.want)
+		}
+	}
+}
+
+func TestNicknameSkeleton(t *testing.T) {
+	tests := []struct {
+		a, b string
+		same bool
+	}{
+		{"admin", "аdmin", true}, // Cyrillic а
+		{"carol", "сarol", true}, // Cyrillic с
+		{"jack", "jасk", true},   // Cyrillic а, с
+		{"lily", "ӏily", true},   // Cyrillic palochka
+		{"ugly", "υgly", true},   // Greek upsilon
+		{"admin", "ＡＤＭＩＮ", true},
+		{"admin", "𝐚𝐝𝐦𝐢𝐧", true},
+		{"bob", "b\u200bob", true}, // zero-width space
+		{"lol", "IoI", true},
+		{"alice", "ALICE", true},
+		{"modem", "modern", true},
+		{"alice", "bob", false},
+		{"kim", "김", false},
+		{"anna", "ann", false},
+	}
+	for _, tt := range tests {
+		if got := nicknameSkeleton(tt.a) == nicknameSkeleton(tt.b); got != tt.same {
+			t.Errorf("nicknameSkeleton(%q) == nicknameSkeleton(%q) is %t, want %t", tt.a, tt.b, got, tt.same)
+		}
+	}
+}
+
+func TestAddClientRejectsLookAlikes(t *testing.T) {
+	discardLogs(t)
+	cs := NewChatServer()
+	alice := NewClient(cs, nil, "alice", 80, 24, "192.0.2.1")
+	if err := cs.AddClient(alice); err != nil {
+		t.Fatalf("AddClient(alice) = %v", err)
+	}
+	for _, nickname := range []string{"alice", "аlice", "ALICE", "aIice"} {
+		if err := cs.AddClient(NewClient(cs, nil, nickname, 80, 24, "192.0.2.2")); err == nil {
+			t.Errorf("AddClient(%q) succeeded while alice is connected", nickname)
+		}
+	}
+	if err := cs.AddClient(NewClient(cs, nil, "bob", 80, 24, "192.0.2.2")); err != nil {
+		t.Errorf("AddClient(bob) = %v", err)
+	}
+
+	cs.RemoveClient(alice)
+	if err := cs.AddClient(NewClient(cs, nil, "аlice", 80, 24, "192.0.2.2")); err != nil {
+		t.Errorf("AddClient(аlice) after alice left = %v", err)
+	}
+}
+
+func TestValidateNickname(t *testing.T) {
+	tests := []struct {
+		nickname string
+		ok       bool
+	}{
+		{"alice", true},
+		{"guest-1", true},
+		{"김철수", true},
+		{"badminton", true},
+		{"Padmini", true},
+		{"lead.minh", true},
+		{"modest", true},
+		{"server", false},
+		{"Server/", false},
+		{"SYSTEM", false},
+		{"admin", false},
+		{"аdmin", false}, // Cyrillic а
+		{"admin/", false},
+		{"/admin", false},
+		{"ad.min", false},
+		{"admin_bob", false},
+		{"superadmin", false},
+		{"x/moderator", false},
+		{"operator42", false},
+	}
+	for _, tt := range tests {
+		if err := ValidateNickname(tt.nickname); (err == nil) != tt.ok {
+			t.Errorf("ValidateNickname(%q) = %v, want ok=%t", tt.nickname, err, tt.ok)
+		}
+	}
+}
+
+func TestMute(t *testing.T) {
+	cs := &ChatServer{muted: make(map[string]time.Time)}
+	cs.Mute("192.0.2.1", time.Minute)
+	cs.Mute("192.0.2.2", -time.Second)
+
+	if left := cs.MutedFor("192.0.2.1"); left <= 0 || left > time.Minute {
+		t.Errorf("MutedFor(muted IP) = %s, want (0, 1m]", left)
+	}
+	if left := cs.MutedFor("192.0.2.2"); left != 0 {
+		t.Errorf("MutedFor(expired IP) = %s, want 0", left)
+	}
+	if left := cs.MutedFor("192.0.2.3"); left != 0 {
+		t.Errorf("MutedFor(other IP) = %s, want 0", left)
+	}
+	if removed := cs.PruneMutes(); removed != 1 {
+		t.Errorf("PruneMutes() = %d, want 1", removed)
+	}
+}
+
+func TestSummarizeNicknames(t *testing.T) {
+	tests := []struct {
+		nicknames []string
+		want      string
+	}{
+		{[]string{"alice"}, "alice"},
+		{[]string{"alice", "alice", "alice"}, "alice"},
+		{[]string{"alice", "bob"}, "2 users (alice, bob)"},
+		{[]string{"a", "b", "c", "d", "e"}, "5 users (a, b, c, d, e)"},
+		{[]string{"a", "b", "a", "c", "b"}, "3 users (a, b, c)"},
+		{[]string{"a", "b", "c", "d", "e", "f"}, "6 users"},
+		{[]string{"a", "b", "c", "d", "e", "e", "e"}, "5 users (a, b, c, d, e)"},
+	}
+	for _, tt := range tests {
+		if got := summarizeNicknames(tt.nicknames); got != tt.want {
+			t.Errorf("summarizeNicknames(%q) = %q, want %q", tt.nicknames, got, tt.want)
+		}
+	}
+}
+
+func TestAnnouncePresence(t *testing.T) {
+	discardLogs(t)
+	cs := NewChatServer()
+	posted := func() []string {
+		cs.mu.RLock()
+		defer cs.mu.RUnlock()
+		var texts []string
+		for _, msg := range cs.messages[1:] { // skip the welcome message
+			texts = append(texts, msg.Text)
+		}
+		return texts
+	}
+
+	cs.AnnouncePresence("alice", true)
+	if got, want := posted(), []string{"alice joined the chat"}; !slices.Equal(got, want) {
+		t.Fatalf("after the first join, messages = %q, want %q", got, want)
+	}
+
+	// Within presenceWindow everything is held back for the summary
+	cs.AnnouncePresence("bob", true)
+	cs.AnnouncePresence("carol", true)
+	cs.AnnouncePresence("bob", false)
+	cs.AnnouncePresence("bob", true)
+	if got := posted(); len(got) != 1 {
+		t.Fatalf("within the window, messages = %q, want only the first join", got)
+	}
+
+	cs.flushPresence()
+	want := []string{"alice joined the chat", "2 users (bob, carol) joined the chat, bob left the chat"}
+	if got := posted(); !slices.Equal(got, want) {
+		t.Fatalf("after the flush, messages = %q, want %q", got, want)
+	}
+
+	// The flush starts a new window, and its timer finds nothing left to post
+	cs.flushPresence()
+	cs.AnnouncePresence("dave", false)
+	if got := posted(); len(got) != 2 {
+		t.Errorf("after a second flush, messages = %q, want %q", got, want)
+	}
+}
+
+func TestLastMessageByIP(t *testing.T) {
+	discardLogs(t)
+	cs := NewChatServer()
+	cs.AppendMessage(Message{Time: time.Now(), Nick: "alice", Text: "first", IP: "192.0.2.1"})
+	cs.AppendMessage(Message{Time: time.Now(), Nick: "bob", Text: "hello", IP: "192.0.2.2"})
+	cs.AppendMessage(Message{Time: time.Now(), Nick: "alice", Text: "second", IP: "192.0.2.1"})
+	cs.AppendSystemMessage("bob left the chat")
+
+	for ip, want := range map[string]string{"192.0.2.1": "second", "192.0.2.2": "hello", "192.0.2.3": ""} {
+		if got := cs.LastMessageByIP(ip); got != want {
+			t.Errorf("LastMessageByIP(%s) = %q, want %q", ip, got, want)
+		}
+	}
+}
+
+func TestKickVoteTally(t *testing.T) {
+	tests := []struct {
+		yes, no int
+		passed  bool
+	}{
+		{1, 0, false},
+		{voteKickQuorum - 1, 0, false}, // below quorum
+		{voteKickQuorum, 0, true},
+		{voteKickQuorum, voteKickQuorum, false}, // tie
+		{voteKickQuorum + 1, voteKickQuorum, true},
+		{voteKickQuorum, voteKickQuorum + 2, false},
+	}
+	for _, tt := range tests {
+		v := &kickVote{votes: make(map[string]bool)}
+		for i := 0; i < tt.yes; i++ {
+			v.votes[fmt.Sprintf("10.0.0.%d", i)] = true
+		}
+		for i := 0; i < tt.no; i++ {
+			v.votes[fmt.Sprintf("10.0.1.%d", i)] = false
+		}
+		yes, no, passed := v.tally()
+		if yes != tt.yes || no != tt.no || passed != tt.passed {
+			t.Errorf("tally() with %d yes, %d no = (%d, %d, %t), want passed=%t", tt.yes, tt.no, yes, no, passed, tt.passed)
+		}
+	}
+}
+
+func TestStartVoteKickCooldown(t *testing.T) {
+	discardLogs(t)
+	cs := NewChatServer()
+	// The system messages below @-mention nobody, so the nil sessions are
+	// never written to
+	alice := NewClient(cs, nil, "alice", 80, 24, "192.0.2.1")
+	bob := NewClient(cs, nil, "bob", 80, 24, "192.0.2.2")
+	carol := NewClient(cs, nil, "carol", 80, 24, "192.0.2.3")
+	for _, c := range []*Client{alice, bob, carol} {
+		if err := cs.AddClient(c); err != nil {
+			t.Fatal(err)
+		}
+	}
+
+	if err := cs.StartVoteKick(alice, "alice"); err == nil {
+		t.Error("StartVoteKick against oneself succeeded")
+	}
+	if err := cs.StartVoteKick(alice, "nobody"); err == nil {
+		t.Error("StartVoteKick against an unknown user succeeded")
+	}
+	if err := cs.StartVoteKick(alice, "bob"); err != nil {
+		t.Fatalf("StartVoteKick(alice, bob) = %v", err)
+	}
+	if err := cs.StartVoteKick(carol, "bob"); err == nil {
+		t.Error("StartVoteKick succeeded while another vote is open")
+	}
+
+	// Close the poll without waiting for its timer
+	cs.mu.Lock()
+	cs.vote = nil
+	cs.mu.Unlock()
+	if err := cs.StartVoteKick(alice, "carol"); err == nil {
+		t.Error("StartVoteKick succeeded during the initiator's cooldown")
+	}
+	if err := cs.StartVoteKick(carol, "bob"); err != nil {
+		t.Errorf("StartVoteKick(carol, bob) = %v", err)
+	}
+
+	cs.mu.Lock()
+	cs.lastVoteKick[alice.ip] = time.Now().Add(-voteKickCooldown)
+	cs.mu.Unlock()
+	if removed := cs.PruneVoteKickCooldowns(); removed != 1 {
+		t.Errorf("PruneVoteKickCooldowns() = %d, want 1", removed)
+	}
+}
+
+func TestTemporaryBan(t *testing.T) {
+	discardLogs(t)
+	bm := NewBanManager()
+	bm.Ban(BanEntry{IP: "192.0.2.1", IssuedBy: "test"})
+	bm.Ban(BanEntry{IP: "192.0.2.2", IssuedBy: "test", Expires: time.Now().Add(time.Minute)})
+	bm.Ban(BanEntry{IP: "192.0.2.3", IssuedBy: "test", Expires: time.Now().Add(-time.Second)})
+
+	for ip, want := range map[string]bool{"192.0.2.1": true, "192.0.2.2": true, "192.0.2.3": false} {
+		if got := bm.IsBanned(ip); got != want {
+			t.Errorf("IsBanned(%s) = %t, want %t", ip, got, want)
+		}
+	}
+	if removed := bm.Cleanup(); removed != 1 {
+		t.Errorf("Cleanup() = %d, want 1", removed)
+	}
+}
+
+func BenchmarkBroadcastFanout(b *testing.B) {
+	for _, subscribers := range []int{100, 1000, 10000} {
+		b.Run(fmt.Sprintf("subscribers=%d", subscribers), func(b *testing.B) {
+			discardLogs(b)
+			cs := NewChatServer()
+			for i := 0; i < subscribers; i++ {
+				ip := fmt.Sprintf("10.0.%d.%d", i/256, i%256)
+				// Clients are never rendered here, so they need no session.
+				// msg must not mention any of them, though: NotifyWithBell
+				// would write the bell to the nil session.
+				if err := cs.AddClient(NewClient(cs, nil, fmt.Sprintf("user-%d", i), 80, 24, ip)); err != nil {
+					b.Fatal(err)
+				}
+			}
+			msg := Message{Nick: "bench", Text: "hello @nobody, how is everyone?", Color: 31, IP: "192.0.2.1"}
+
+			b.ResetTimer()
+			for i := 0; i < b.N; i++ {
+				msg.Time = time.Now()
+				cs.AppendMessage(msg)
+			}
+		})
+	}
+}
+
+func BenchmarkFormatMessage(b *testing.B) {
+	msg := Message{
+		Time:     time.Now(),
+		Nick:     "guest-1",
+		Text:     strings.Repeat("안녕하세요 hello @guest-2 ", 8),
+		Color:    32,
+		Mentions: []string{"guest-2"},
+	}
+	msg.prerender()
+
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		formatMessage(msg, 80, time.UTC)
+	}
+}
+
+func BenchmarkBanCheck(b *testing.B) {
+	discardLogs(b)
+	bm := NewBanManager()
+	for i := 0; i < 10000; i++ {
+		bm.Ban(BanEntry{IP: fmt.Sprintf("10.%d.%d.1", i/256, i%256), IssuedBy: "bench"})
+	}
+
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		bm.IsBanned("10.20.30.1")
+		bm.IsBanned("192.0.2.1")
+	}
+}
+
+func BenchmarkConnectionRateLimiter(b *testing.B) {
+	ips := make([]string, 65536)
+	for i := range ips {
+		ips[i] = fmt.Sprintf("10.0.%d.%d", i/256, i%256)
+	}
+	rl := NewConnectionRateLimiter(5)
+
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		if i > 0 && i%len(ips) == 0 {
+			// Start over so IPs never reach the limit and every call takes the
+			// same path, whatever b.N is
+			b.StopTimer()
+			rl = NewConnectionRateLimiter(5)
+			b.StartTimer()
+		}
+		rl.CheckAndRecord(ips[i%len(ips)])
+	}
+}
+
+func BenchmarkValidateMessage(b *testing.B) {
+	text := strings.Repeat("ㅋㅋㅋ 그거 진짜 웃기네요 lol ", 10)
+
+	b.ResetTimer()
+	for i := 0; i < b.N; i++ {
+		if err := ValidateMessage(text); err != nil {
+			b.Fatal(err)
+		}
+	}
+}
+
+func BenchmarkNicknameSkeleton(b *testing.B) {
+	for i := 0; i < b.N; i++ {
+		nicknameSkeleton("аdmin-ＴＥＳＴ")
+	}
+}